@@ -3,17 +3,30 @@ use crate::gpu::GPUSelector;
 use crate::poseidon::SimplePoseidonBatchHasher;
 use crate::{Arity, BatchHasher, Strength, DEFAULT_STRENGTH};
 use generic_array::GenericArray;
+use log::{debug, info};
 use paired::bls12_381::Fr;
 use std::marker::PhantomData;
+use std::mem::size_of;
 
 #[cfg(all(feature = "gpu", not(target_os = "macos")))]
 use crate::cl;
 
+#[cfg(all(feature = "gpu", target_os = "macos"))]
+use crate::metal::MetalBatchHasher;
+
+#[cfg(feature = "webgpu")]
+use crate::webgpu::WgpuBatchHasher;
+
 #[derive(Clone, Copy, Debug)]
 pub enum BatcherType {
     CustomGPU(GPUSelector),
     GPU,
+    WebGPU,
     CPU,
+    /// Probes available backends in priority order (a preferred `GPUSelector` if
+    /// one is supplied, the default GPU backend, WebGPU, then CPU) and resolves
+    /// to whichever initializes successfully. See [`Batcher::new_auto`].
+    Auto(Option<GPUSelector>),
 }
 
 #[cfg(not(target_os = "macos"))]
@@ -25,11 +38,59 @@ where
 {
     #[cfg(not(target_os = "macos"))]
     GPU(GPUBatchHasher<A>),
-    #[cfg(target_os = "macos")]
+    #[cfg(all(feature = "gpu", target_os = "macos"))]
+    GPU(MetalBatchHasher<A>),
+    #[cfg(all(not(feature = "gpu"), target_os = "macos"))]
     GPU(NoGPUBatchHasher<A>),
+    #[cfg(feature = "webgpu")]
+    WebGPU(WgpuBatchHasher<A>),
     CPU(SimplePoseidonBatchHasher<A>),
 }
 
+/// Queried properties of the backend a [`Batcher`] is running on, used by
+/// [`Batcher::recommended_batch_size`] to size batches to the actual hardware
+/// instead of the caller guessing a `max_batch_size` and risking an OOM.
+///
+/// Fields are `None`/conservative defaults when a backend cannot report them --
+/// notably the legacy Futhark/OpenCL `GPU` backend on non-macOS, which predates
+/// this query and is not itself modified here.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeviceInfo {
+    /// Total device memory in bytes, if known.
+    pub total_memory: Option<u64>,
+    /// Memory currently available for allocation, in bytes, if known.
+    pub available_memory: Option<u64>,
+    /// Whether the device shares physical memory with the host (e.g. Apple
+    /// Silicon, or the CPU backend itself), avoiding a staging-buffer copy.
+    pub has_unified_memory: bool,
+    /// Maximum number of threads/invocations per workgroup the backend supports.
+    pub max_workgroup_size: usize,
+    /// Subgroup (SIMD-group / wave) width, if the backend exposes one.
+    pub subgroup_size: Option<usize>,
+}
+
+/// Used by [`DeviceInfo::recommended_batch_size`] when a backend cannot report
+/// any memory figures at all (e.g. the legacy Futhark/OpenCL backend).
+const FALLBACK_BATCH_SIZE: usize = 1000;
+
+impl DeviceInfo {
+    /// Derives a safe `max_batch_size` from these device limits, given the
+    /// per-preimage byte width (`(A::to_usize() + 1) * size_of::<Fr>()`). Reserves
+    /// headroom for the staging buffer and blit copy a non-unified-memory device
+    /// needs, since preimages, and their digests, are resident at the same time.
+    pub fn recommended_batch_size(&self, bytes_per_preimage: usize) -> usize {
+        let bytes_per_preimage = bytes_per_preimage.max(1) as u64;
+
+        let usable_memory = match self.available_memory.or(self.total_memory) {
+            Some(bytes) if self.has_unified_memory => bytes * 3 / 4,
+            Some(bytes) => bytes / 2,
+            None => return FALLBACK_BATCH_SIZE,
+        };
+
+        ((usable_memory / bytes_per_preimage) as usize).max(1)
+    }
+}
+
 impl<A> Batcher<A>
 where
     A: Arity<Fr>,
@@ -37,6 +98,8 @@ where
     pub(crate) fn t(&self) -> BatcherType {
         match self {
             Batcher::GPU(_) => BatcherType::GPU,
+            #[cfg(feature = "webgpu")]
+            Batcher::WebGPU(_) => BatcherType::WebGPU,
             Batcher::CPU(_) => BatcherType::CPU,
         }
     }
@@ -45,16 +108,71 @@ where
         Self::new_with_strength(DEFAULT_STRENGTH, t, max_batch_size)
     }
 
+    /// Resolves `BatcherType::Auto(selector)` at the default strength. See
+    /// [`Batcher::new_auto_with_strength`] for the probing order.
+    pub(crate) fn new_auto(selector: Option<GPUSelector>, max_batch_size: usize) -> Result<Self, Error> {
+        Self::new_auto_with_strength(DEFAULT_STRENGTH, selector, max_batch_size)
+    }
+
+    /// Probes backends in priority order -- `selector` (if supplied), the default
+    /// GPU backend, WebGPU (when the `webgpu` feature is enabled), then CPU -- and
+    /// returns the first one that initializes successfully, logging which backend
+    /// was chosen. Trying `WebGPU` before falling back to `CPU` matters
+    /// specifically on hosts with no OpenCL/Futhark stack and no macOS Metal
+    /// device (e.g. Linux without an ICD, or `wasm32`), where it's the only GPU
+    /// path that can actually succeed. This spares every caller of `Batcher::new`
+    /// the boilerplate of catching a GPU initialization error and retrying with
+    /// `BatcherType::CPU` itself.
+    pub(crate) fn new_auto_with_strength(
+        strength: Strength,
+        selector: Option<GPUSelector>,
+        max_batch_size: usize,
+    ) -> Result<Self, Error> {
+        let mut candidates = Vec::new();
+        if let Some(selector) = selector {
+            candidates.push(BatcherType::CustomGPU(selector));
+        }
+        candidates.push(BatcherType::GPU);
+        #[cfg(feature = "webgpu")]
+        candidates.push(BatcherType::WebGPU);
+        candidates.push(BatcherType::CPU);
+
+        for candidate in candidates {
+            match Self::new_with_strength(strength, &candidate, max_batch_size) {
+                Ok(batcher) => {
+                    info!("Batcher::new_auto: selected {:?} backend", batcher.t());
+                    return Ok(batcher);
+                }
+                Err(e) => {
+                    debug!("Batcher::new_auto: backend {:?} unavailable: {}", candidate, e);
+                }
+            }
+        }
+
+        Err(Error::GPUError(
+            "Batcher::new_auto: no backend (GPU or CPU) could be initialized".to_string(),
+        ))
+    }
+
     pub(crate) fn new_with_strength(
         strength: Strength,
         t: &BatcherType,
         max_batch_size: usize,
     ) -> Result<Self, Error> {
         match t {
+            BatcherType::Auto(selector) => {
+                Self::new_auto_with_strength(strength, *selector, max_batch_size)
+            }
             #[cfg(all(feature = "gpu", target_os = "macos"))]
-            BatcherType::GPU => panic!("GPU unimplemented on macos"),
+            BatcherType::GPU => Ok(Batcher::GPU(MetalBatchHasher::<A>::new_with_strength(
+                strength,
+                max_batch_size,
+            )?)),
             #[cfg(all(feature = "gpu", target_os = "macos"))]
-            BatcherType::CustomGPU(_) => panic!("GPU unimplemented on macos"),
+            BatcherType::CustomGPU(_) => Ok(Batcher::GPU(MetalBatchHasher::<A>::new_with_strength(
+                strength,
+                max_batch_size,
+            )?)),
             #[cfg(all(feature = "gpu", not(target_os = "macos")))]
             BatcherType::GPU => Ok(Batcher::GPU(GPUBatchHasher::<A>::new_with_strength(
                 cl::default_futhark_context()?,
@@ -70,11 +188,43 @@ where
                 )?))
             }
 
+            #[cfg(feature = "webgpu")]
+            BatcherType::WebGPU => Ok(Batcher::WebGPU(
+                WgpuBatchHasher::<A>::new_with_strength(strength, max_batch_size)?,
+            )),
+            #[cfg(not(feature = "webgpu"))]
+            BatcherType::WebGPU => Err(Error::GPUError(
+                "webgpu feature not enabled; rebuild with --features webgpu".to_string(),
+            )),
+
             BatcherType::CPU => Ok(Batcher::CPU(
                 SimplePoseidonBatchHasher::<A>::new_with_strength(strength, max_batch_size)?,
             )),
         }
     }
+
+    /// Queried properties of the backend this `Batcher` is actually running on.
+    pub fn device_info(&self) -> DeviceInfo {
+        match self {
+            #[cfg(all(feature = "gpu", target_os = "macos"))]
+            Batcher::GPU(batcher) => batcher.device_info(),
+            #[cfg(all(not(feature = "gpu"), target_os = "macos"))]
+            Batcher::GPU(batcher) => batcher.device_info(),
+            #[cfg(not(target_os = "macos"))]
+            Batcher::GPU(_) => DeviceInfo::default(),
+            #[cfg(feature = "webgpu")]
+            Batcher::WebGPU(batcher) => batcher.device_info(),
+            Batcher::CPU(batcher) => batcher.device_info(),
+        }
+    }
+
+    /// Derives a safe `max_batch_size` for this `Batcher`'s backend and `Arity`
+    /// from [`Batcher::device_info`], rather than the caller guessing one and
+    /// risking an over-large batch silently OOMing the GPU.
+    pub fn recommended_batch_size(&self) -> usize {
+        let bytes_per_preimage = (A::to_usize() + 1) * size_of::<Fr>();
+        self.device_info().recommended_batch_size(bytes_per_preimage)
+    }
 }
 
 impl<A> BatchHasher<A> for Batcher<A>
@@ -84,6 +234,8 @@ where
     fn hash(&mut self, preimages: &[GenericArray<Fr, A>]) -> Result<Vec<Fr>, Error> {
         match self {
             Batcher::GPU(batcher) => batcher.hash(preimages),
+            #[cfg(feature = "webgpu")]
+            Batcher::WebGPU(batcher) => batcher.hash(preimages),
             Batcher::CPU(batcher) => batcher.hash(preimages),
         }
     }
@@ -91,6 +243,8 @@ where
     fn max_batch_size(&self) -> usize {
         match self {
             Batcher::GPU(batcher) => batcher.max_batch_size(),
+            #[cfg(feature = "webgpu")]
+            Batcher::WebGPU(batcher) => batcher.max_batch_size(),
             Batcher::CPU(batcher) => batcher.max_batch_size(),
         }
     }
@@ -112,3 +266,70 @@ where
         unimplemented!();
     }
 }
+
+impl<A> NoGPUBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    pub(crate) fn device_info(&self) -> DeviceInfo {
+        unimplemented!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typenum::U2;
+
+    #[test]
+    fn recommended_batch_size_uses_available_memory_and_unified_fraction() {
+        let info = DeviceInfo {
+            total_memory: Some(1_000_000),
+            available_memory: Some(800_000),
+            has_unified_memory: true,
+            max_workgroup_size: 256,
+            subgroup_size: Some(32),
+        };
+        // Unified memory: 3/4 of `available_memory` (preferred over `total_memory`).
+        assert_eq!(info.recommended_batch_size(100), 800_000 * 3 / 4 / 100);
+    }
+
+    #[test]
+    fn recommended_batch_size_uses_half_memory_when_not_unified() {
+        let info = DeviceInfo {
+            total_memory: Some(1_000_000),
+            available_memory: None,
+            has_unified_memory: false,
+            max_workgroup_size: 256,
+            subgroup_size: None,
+        };
+        assert_eq!(info.recommended_batch_size(100), 1_000_000 / 2 / 100);
+    }
+
+    #[test]
+    fn recommended_batch_size_falls_back_when_no_memory_is_known() {
+        let info = DeviceInfo::default();
+        assert_eq!(info.recommended_batch_size(100), FALLBACK_BATCH_SIZE);
+    }
+
+    #[test]
+    fn new_auto_with_strength_falls_back_to_cpu_without_a_gpu_selector() {
+        // On a host with no GPU/WebGPU backend compiled in (or none available),
+        // `Auto` must still resolve by falling back to `BatcherType::CPU` rather
+        // than returning an error.
+        let batcher = Batcher::<U2>::new_auto_with_strength(DEFAULT_STRENGTH, None, 10)
+            .expect("CPU backend is always available");
+        assert!(matches!(batcher.t(), BatcherType::CPU));
+    }
+
+    #[test]
+    fn batcher_device_info_and_recommended_batch_size_dispatch_to_the_backend() {
+        let batcher = Batcher::<U2>::new_with_strength(DEFAULT_STRENGTH, &BatcherType::CPU, 10)
+            .expect("CPU backend is always available");
+
+        let info = batcher.device_info();
+        assert!(info.has_unified_memory);
+        assert!(info.max_workgroup_size > 0);
+        assert!(batcher.recommended_batch_size() > 0);
+    }
+}
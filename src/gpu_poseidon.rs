@@ -0,0 +1,66 @@
+use crate::poseidon::PoseidonConstants;
+use crate::Arity;
+use ff::PrimeFieldRepr;
+use paired::bls12_381::Fr;
+
+/// Round-schedule shape shared by every `poseidon_permute`-style GPU kernel
+/// (Metal, WebGPU). Kept separate from the packed constant bytes themselves so
+/// callers can bind it as a small uniform alongside the much larger constants
+/// buffer.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GpuPoseidonLayout {
+    /// State width, i.e. `Arity::to_usize() + 1` (the capacity element plus one
+    /// slot per preimage element).
+    pub width: u32,
+    /// Total number of full rounds (split evenly before/after the partial rounds).
+    pub full_rounds: u32,
+    /// Number of partial rounds between the two full-round halves.
+    pub partial_rounds: u32,
+}
+
+/// Packs `constants.round_constants` and `constants.mds_matrices.m` -- the same
+/// tables [`crate::poseidon::SimplePoseidonBatchHasher`]'s CPU path uses -- into
+/// the flat little-endian `Fr` layout every `poseidon_permute` GPU kernel expects:
+/// `[domain_tag][round_constants...][mds row 0][mds row 1]...`.
+///
+/// The GPU kernels apply the *full* MDS matrix on every round rather than the
+/// sparse-matrix partial-round optimization the CPU path may use internally;
+/// the two are algebraically equivalent (that equivalence is the basis of the
+/// optimization), so this produces the same digests at the cost of more
+/// multiplications per partial round.
+pub(crate) fn pack_constants<A>(constants: &PoseidonConstants<Fr, A>) -> (Vec<u8>, GpuPoseidonLayout)
+where
+    A: Arity<Fr>,
+{
+    let width = A::to_usize() + 1;
+    let mut bytes = Vec::with_capacity((1 + constants.round_constants.len() + width * width) * 32);
+
+    constants
+        .domain_tag
+        .into_repr()
+        .write_le(&mut bytes)
+        .expect("writing Fr repr into constants buffer cannot fail");
+
+    for rc in &constants.round_constants {
+        rc.into_repr()
+            .write_le(&mut bytes)
+            .expect("writing Fr repr into constants buffer cannot fail");
+    }
+
+    for row in &constants.mds_matrices.m {
+        for elt in row {
+            elt.into_repr()
+                .write_le(&mut bytes)
+                .expect("writing Fr repr into constants buffer cannot fail");
+        }
+    }
+
+    (
+        bytes,
+        GpuPoseidonLayout {
+            width: width as u32,
+            full_rounds: constants.full_rounds as u32,
+            partial_rounds: constants.partial_rounds as u32,
+        },
+    )
+}
@@ -0,0 +1,279 @@
+use crate::error::Error;
+use crate::gpu_poseidon::{pack_constants, GpuPoseidonLayout};
+use crate::poseidon::PoseidonConstants;
+use crate::{Arity, BatchHasher, DeviceInfo, Strength};
+use ff::{PrimeField, PrimeFieldRepr};
+use generic_array::GenericArray;
+use metal::{Buffer, CommandQueue, ComputePipelineState, Device, MTLResourceOptions, MTLSize};
+use paired::bls12_381::{Fr, FrRepr};
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+const POSEIDON_METAL_SOURCE: &str = include_str!("metal/poseidon.metal");
+
+/// Mirrors the Metal kernel's `Layout` struct byte-for-byte so it can be handed
+/// to `new_buffer_with_data` without a manual field-by-field pack.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct KernelLayout {
+    width: u32,
+    full_rounds: u32,
+    partial_rounds: u32,
+}
+
+impl From<GpuPoseidonLayout> for KernelLayout {
+    fn from(layout: GpuPoseidonLayout) -> Self {
+        Self {
+            width: layout.width,
+            full_rounds: layout.full_rounds,
+            partial_rounds: layout.partial_rounds,
+        }
+    }
+}
+
+/// A `BatchHasher` backed by a Metal compute kernel, used on macOS/iOS where no
+/// OpenCL/Futhark stack is available. The Poseidon round functions for the
+/// requested `Strength`/`Arity` are compiled into a compute pipeline once at
+/// construction time and reused for every `hash` call; the round constants and
+/// MDS matrix for that `Strength`/`Arity` are uploaded once as a constants
+/// buffer alongside it.
+pub struct MetalBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    device: Device,
+    queue: CommandQueue,
+    pipeline: ComputePipelineState,
+    constants_buffer: Buffer,
+    layout: KernelLayout,
+    has_unified_memory: bool,
+    max_batch_size: usize,
+    _a: PhantomData<A>,
+}
+
+impl<A> MetalBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    pub(crate) fn new(max_batch_size: usize) -> Result<Self, Error> {
+        Self::new_with_strength(Strength::Standard, max_batch_size)
+    }
+
+    pub(crate) fn new_with_strength(
+        strength: Strength,
+        max_batch_size: usize,
+    ) -> Result<Self, Error> {
+        let device = Device::system_default()
+            .ok_or_else(|| Error::GPUError("no Metal device available".to_string()))?;
+
+        let library = device
+            .new_library_with_source(POSEIDON_METAL_SOURCE, &metal::CompileOptions::new())
+            .map_err(|e| Error::GPUError(format!("failed to compile Poseidon kernel: {}", e)))?;
+
+        let function = library
+            .get_function("poseidon_permute", None)
+            .map_err(|e| Error::GPUError(format!("missing Metal function poseidon_permute: {}", e)))?;
+
+        let pipeline = device
+            .new_compute_pipeline_state_with_function(&function)
+            .map_err(|e| Error::GPUError(format!("failed to build pipeline state: {}", e)))?;
+
+        let queue = device.new_command_queue();
+
+        let poseidon_constants = PoseidonConstants::<Fr, A>::new_with_strength(strength);
+        let (constants_bytes, layout) = pack_constants(&poseidon_constants);
+        let constants_buffer = device.new_buffer_with_data(
+            constants_bytes.as_ptr() as *const _,
+            constants_bytes.len() as u64,
+            MTLResourceOptions::StorageModeShared,
+        );
+
+        Ok(Self {
+            has_unified_memory: device.has_unified_memory(),
+            device,
+            queue,
+            pipeline,
+            constants_buffer,
+            layout: layout.into(),
+            max_batch_size,
+            _a: PhantomData,
+        })
+    }
+
+    fn pack_preimages(&self, preimages: &[GenericArray<Fr, A>]) -> Vec<u8> {
+        let mut packed = Vec::with_capacity(preimages.len() * A::to_usize() * size_of::<FrRepr>());
+        for preimage in preimages {
+            for elt in preimage.iter() {
+                elt.into_repr()
+                    .write_le(&mut packed)
+                    .expect("writing Fr repr into packed buffer cannot fail");
+            }
+        }
+        packed
+    }
+
+    /// On unified-memory GPUs (most Apple Silicon), the CPU and GPU share the same
+    /// physical memory, so we can write preimages directly into a `Shared` buffer
+    /// with no separate staging/blit step. On discrete GPUs we instead allocate a
+    /// `Private` buffer and upload into it from a staging buffer with a blit
+    /// encoder. Either way, `hash` writes digests to a separate `Shared` output
+    /// buffer and reads them from it directly.
+    fn make_input_buffer(&self, data: &[u8]) -> Buffer {
+        if self.has_unified_memory {
+            self.device.new_buffer_with_data(
+                data.as_ptr() as *const _,
+                data.len() as u64,
+                MTLResourceOptions::StorageModeShared,
+            )
+        } else {
+            let staging = self.device.new_buffer_with_data(
+                data.as_ptr() as *const _,
+                data.len() as u64,
+                MTLResourceOptions::StorageModeShared,
+            );
+            let private = self
+                .device
+                .new_buffer(data.len() as u64, MTLResourceOptions::StorageModePrivate);
+
+            let blit_buffer = self.queue.new_command_buffer();
+            let blit_encoder = blit_buffer.new_blit_command_encoder();
+            blit_encoder.copy_from_buffer(&staging, 0, &private, 0, data.len() as u64);
+            blit_encoder.end_encoding();
+            blit_buffer.commit();
+            blit_buffer.wait_until_completed();
+
+            private
+        }
+    }
+
+    /// Derives device limits by querying the Metal device and pipeline state,
+    /// rather than hardcoding them; `has_unified_memory` is the value recorded at
+    /// construction time.
+    pub(crate) fn device_info(&self) -> DeviceInfo {
+        let working_set = self.device.recommended_max_working_set_size();
+
+        DeviceInfo {
+            total_memory: Some(working_set),
+            available_memory: Some(working_set),
+            has_unified_memory: self.has_unified_memory,
+            max_workgroup_size: self.pipeline.max_total_threads_per_threadgroup() as usize,
+            subgroup_size: Some(self.pipeline.thread_execution_width() as usize),
+        }
+    }
+}
+
+impl<A> BatchHasher<A> for MetalBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    fn hash(&mut self, preimages: &[GenericArray<Fr, A>]) -> Result<Vec<Fr>, Error> {
+        let packed = self.pack_preimages(preimages);
+        let input_buffer = self.make_input_buffer(&packed);
+        let output_len = preimages.len() * size_of::<FrRepr>();
+        let output_buffer = self
+            .device
+            .new_buffer(output_len.max(1) as u64, MTLResourceOptions::StorageModeShared);
+
+        let num_preimages = preimages.len() as u32;
+        let num_preimages_buffer = self.device.new_buffer_with_data(
+            &num_preimages as *const u32 as *const _,
+            size_of::<u32>() as u64,
+            MTLResourceOptions::StorageModeShared,
+        );
+        let layout_buffer = self.device.new_buffer_with_data(
+            &self.layout as *const KernelLayout as *const _,
+            size_of::<KernelLayout>() as u64,
+            MTLResourceOptions::StorageModeShared,
+        );
+
+        let command_buffer = self.queue.new_command_buffer();
+        let encoder = command_buffer.new_compute_command_encoder();
+        encoder.set_compute_pipeline_state(&self.pipeline);
+        encoder.set_buffer(0, Some(&input_buffer), 0);
+        encoder.set_buffer(1, Some(&output_buffer), 0);
+        encoder.set_buffer(2, Some(&self.constants_buffer), 0);
+        encoder.set_buffer(3, Some(&layout_buffer), 0);
+        encoder.set_buffer(4, Some(&num_preimages_buffer), 0);
+
+        let threads_per_group = MTLSize::new(self.pipeline.thread_execution_width(), 1, 1);
+        let group_count = MTLSize::new(
+            (preimages.len() as u64 + threads_per_group.width - 1) / threads_per_group.width,
+            1,
+            1,
+        );
+        encoder.dispatch_thread_groups(group_count, threads_per_group);
+        encoder.end_encoding();
+
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        let out_ptr = output_buffer.contents() as *const FrRepr;
+        let results = (0..preimages.len())
+            .map(|i| {
+                let repr = unsafe { *out_ptr.add(i) };
+                Fr::from_repr(repr)
+                    .map_err(|e| Error::GPUError(format!("invalid Fr returned from Metal kernel: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(results)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::SimplePoseidonBatchHasher;
+    use crate::typenum::{U2, U4, U8, U11};
+    use generic_array::sequence::GenericSequence;
+
+    fn sample_preimages<A: Arity<Fr>>(count: usize) -> Vec<GenericArray<Fr, A>> {
+        (0..count)
+            .map(|i| {
+                GenericArray::generate(|j| {
+                    Fr::from_str(&(i * 1000 + j + 1).to_string()).expect("valid Fr literal")
+                })
+            })
+            .collect()
+    }
+
+    fn assert_matches_cpu<A: Arity<Fr>>() {
+        let preimages = sample_preimages::<A>(37);
+
+        let mut metal_hasher =
+            MetalBatchHasher::<A>::new_with_strength(Strength::Standard, preimages.len())
+                .expect("Metal device available in CI");
+        let mut cpu_hasher =
+            SimplePoseidonBatchHasher::<A>::new_with_strength(Strength::Standard, preimages.len())
+                .expect("CPU hasher always available");
+
+        let gpu_digests = metal_hasher.hash(&preimages).expect("Metal hash succeeds");
+        let cpu_digests = cpu_hasher.hash(&preimages).expect("CPU hash succeeds");
+
+        assert_eq!(gpu_digests, cpu_digests);
+    }
+
+    #[test]
+    fn metal_matches_cpu_arity_2() {
+        assert_matches_cpu::<U2>();
+    }
+
+    #[test]
+    fn metal_matches_cpu_arity_4() {
+        assert_matches_cpu::<U4>();
+    }
+
+    #[test]
+    fn metal_matches_cpu_arity_8() {
+        assert_matches_cpu::<U8>();
+    }
+
+    #[test]
+    fn metal_matches_cpu_arity_11() {
+        assert_matches_cpu::<U11>();
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::Error;
+use crate::{Arity, BatchHasher, DeviceInfo, Poseidon, PoseidonConstants, Strength};
+use generic_array::GenericArray;
+use paired::bls12_381::Fr;
+
+#[cfg(feature = "multicore")]
+use rayon::prelude::*;
+
+/// A `BatchHasher` that computes each preimage's Poseidon permutation directly on
+/// the CPU, with no GPU involved. This is the always-available fallback backend
+/// for [`crate::Batcher`] -- every other variant delegates to one GPU API or
+/// another, while this one only needs [`Poseidon`] itself.
+///
+/// With the `multicore` feature enabled, the batch is hashed in parallel with
+/// rayon (each preimage's permutation is independent of the others), including on
+/// `wasm32-unknown-unknown` builds compiled with `+atomics,+bulk-memory` and
+/// initialized via [`init_thread_pool`].
+pub struct SimplePoseidonBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    constants: PoseidonConstants<Fr, A>,
+    max_batch_size: usize,
+}
+
+impl<A> SimplePoseidonBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    pub(crate) fn new(max_batch_size: usize) -> Result<Self, Error> {
+        Self::new_with_strength(Strength::Standard, max_batch_size)
+    }
+
+    pub(crate) fn new_with_strength(
+        strength: Strength,
+        max_batch_size: usize,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            constants: PoseidonConstants::<Fr, A>::new_with_strength(strength),
+            max_batch_size,
+        })
+    }
+}
+
+impl<A> BatchHasher<A> for SimplePoseidonBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    #[cfg(feature = "multicore")]
+    fn hash(&mut self, preimages: &[GenericArray<Fr, A>]) -> Result<Vec<Fr>, Error> {
+        Ok(preimages
+            .par_iter()
+            .map(|preimage| Poseidon::new_with_preimage(preimage, &self.constants).hash())
+            .collect())
+    }
+
+    #[cfg(not(feature = "multicore"))]
+    fn hash(&mut self, preimages: &[GenericArray<Fr, A>]) -> Result<Vec<Fr>, Error> {
+        Ok(preimages
+            .iter()
+            .map(|preimage| Poseidon::new_with_preimage(preimage, &self.constants).hash())
+            .collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+}
+
+impl<A> SimplePoseidonBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    /// The CPU backend shares memory with the host by definition and has no
+    /// workgroup/subgroup concept, so `max_workgroup_size` reports the number of
+    /// preimages rayon can realistically hash at once: the available parallelism.
+    pub(crate) fn device_info(&self) -> DeviceInfo {
+        let parallelism = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        DeviceInfo {
+            total_memory: None,
+            available_memory: None,
+            has_unified_memory: true,
+            max_workgroup_size: parallelism,
+            subgroup_size: None,
+        }
+    }
+}
+
+/// Initializes the rayon thread pool backing the `multicore` feature's parallel
+/// `hash` on `wasm32-unknown-unknown`. Must be called (and awaited, from JS) once
+/// before the first `SimplePoseidonBatchHasher::hash`, since the wasm thread pool
+/// cannot bootstrap itself the way a native rayon pool does. Unused -- and
+/// unnecessary -- on native targets, where rayon's global pool spins up lazily.
+#[cfg(all(target_arch = "wasm32", feature = "multicore"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typenum::U4;
+    use ff::PrimeField;
+    use generic_array::sequence::GenericSequence;
+
+    fn sample_preimages(count: usize) -> Vec<GenericArray<Fr, U4>> {
+        (0..count)
+            .map(|i| GenericArray::generate(|j| Fr::from_str(&(i * 10 + j + 1).to_string()).unwrap()))
+            .collect()
+    }
+
+    /// `hash`'s rayon `par_iter` path (the `multicore` feature, which this crate
+    /// builds with in CI) must produce the same digests, in the same order, as
+    /// hashing each preimage serially -- including for a batch size that isn't a
+    /// multiple of the available parallelism, which is what would expose an
+    /// accidental reordering from `par_iter`.
+    #[test]
+    fn multicore_hash_matches_serial_order() {
+        let preimages = sample_preimages(
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                * 2
+                + 1,
+        );
+
+        let mut hasher = SimplePoseidonBatchHasher::<U4>::new_with_strength(Strength::Standard, preimages.len())
+            .expect("CPU hasher always available");
+        let parallel_digests = hasher.hash(&preimages).expect("multicore hash succeeds");
+
+        let serial_digests: Vec<Fr> = preimages
+            .iter()
+            .map(|preimage| Poseidon::new_with_preimage(preimage, &hasher.constants).hash())
+            .collect();
+
+        assert_eq!(parallel_digests, serial_digests);
+    }
+}
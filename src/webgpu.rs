@@ -0,0 +1,386 @@
+use crate::error::Error;
+use crate::gpu_poseidon::pack_constants;
+use crate::poseidon::PoseidonConstants;
+use crate::{Arity, BatchHasher, DeviceInfo, Strength};
+use ff::PrimeFieldRepr;
+use generic_array::GenericArray;
+use paired::bls12_381::Fr;
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+const WORKGROUP_SIZE: u64 = 64;
+
+const POSEIDON_WGSL_SOURCE: &str = include_str!("webgpu/poseidon.wgsl");
+
+/// Mirrors the WGSL kernel's `Layout` uniform struct byte-for-byte.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct KernelLayout {
+    width: u32,
+    full_rounds: u32,
+    partial_rounds: u32,
+    num_preimages: u32,
+}
+
+unsafe impl bytemuck::Pod for KernelLayout {}
+unsafe impl bytemuck::Zeroable for KernelLayout {}
+
+/// A `BatchHasher` backed by a portable WebGPU compute shader (via `wgpu`), running
+/// on Vulkan, Metal, DX12 or GLES -- including `wasm32` targets in-browser. This is
+/// the GPU backend to reach for whenever the Futhark/OpenCL stack in [`crate::cl`]
+/// isn't available, at the cost of depending on whatever `wgpu` backend the host
+/// picks.
+pub struct WgpuBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    constants_buffer: wgpu::Buffer,
+    layout: KernelLayout,
+    has_unified_memory: bool,
+    max_batch_size: usize,
+    _a: PhantomData<A>,
+}
+
+impl<A> WgpuBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    pub(crate) fn new(max_batch_size: usize) -> Result<Self, Error> {
+        Self::new_with_strength(Strength::Standard, max_batch_size)
+    }
+
+    pub(crate) fn new_with_strength(
+        strength: Strength,
+        max_batch_size: usize,
+    ) -> Result<Self, Error> {
+        use wgpu::util::DeviceExt;
+
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| Error::GPUError("no compatible WebGPU adapter found".to_string()))?;
+
+        // `IntegratedGpu`/`Cpu` adapters share physical memory with the host (no
+        // staging-buffer copy needed); `DiscreteGpu`/`VirtualGpu`/`Other` don't.
+        let has_unified_memory = matches!(
+            adapter.get_info().device_type,
+            wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::Cpu
+        );
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("neptune-poseidon-device"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        ))
+        .map_err(|e| Error::GPUError(format!("failed to acquire WebGPU device: {}", e)))?;
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("poseidon-shader"),
+            source: wgpu::ShaderSource::Wgsl(POSEIDON_WGSL_SOURCE.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("poseidon-bind-group-layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("poseidon-pipeline-layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("poseidon-pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "poseidon_permute",
+        });
+
+        let poseidon_constants = PoseidonConstants::<Fr, A>::new_with_strength(strength);
+        let (constants_bytes, gpu_layout) = pack_constants(&poseidon_constants);
+        let constants_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("poseidon-constants"),
+            contents: &constants_bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            bind_group_layout,
+            constants_buffer,
+            layout: KernelLayout {
+                width: gpu_layout.width,
+                full_rounds: gpu_layout.full_rounds,
+                partial_rounds: gpu_layout.partial_rounds,
+                num_preimages: 0,
+            },
+            has_unified_memory,
+            max_batch_size,
+            _a: PhantomData,
+        })
+    }
+
+    fn preimage_width(&self) -> usize {
+        A::to_usize()
+    }
+
+    /// Derives device limits from the adapter/device's reported feature-set
+    /// limits. `wgpu` does not expose a portable memory-size query across all of
+    /// Vulkan/Metal/DX12/GLES/wasm32, so `total_memory`/`available_memory` are
+    /// left unknown here; callers fall back to [`DeviceInfo`]'s conservative
+    /// default in that case. `has_unified_memory` is derived from the adapter's
+    /// `DeviceType`, queried once at construction time.
+    pub(crate) fn device_info(&self) -> DeviceInfo {
+        let limits = self.device.limits();
+
+        DeviceInfo {
+            total_memory: None,
+            available_memory: None,
+            has_unified_memory: self.has_unified_memory,
+            max_workgroup_size: limits.max_compute_workgroup_size_x as usize,
+            subgroup_size: None,
+        }
+    }
+
+    fn pack_preimages(&self, preimages: &[GenericArray<Fr, A>]) -> Vec<u8> {
+        use ff::PrimeField;
+
+        let mut packed =
+            Vec::with_capacity(preimages.len() * self.preimage_width() * size_of::<Fr>());
+        for preimage in preimages {
+            for elt in preimage.iter() {
+                elt.into_repr()
+                    .write_le(&mut packed)
+                    .expect("writing Fr repr into packed buffer cannot fail");
+            }
+        }
+        packed
+    }
+}
+
+impl<A> BatchHasher<A> for WgpuBatchHasher<A>
+where
+    A: Arity<Fr>,
+{
+    fn hash(&mut self, preimages: &[GenericArray<Fr, A>]) -> Result<Vec<Fr>, Error> {
+        use ff::PrimeField;
+        use wgpu::util::DeviceExt;
+
+        let packed = self.pack_preimages(preimages);
+        let input_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("poseidon-preimages"),
+            contents: &packed,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let output_size = (preimages.len() * size_of::<Fr>()).max(size_of::<Fr>()) as u64;
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("poseidon-digests"),
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("poseidon-staging"),
+            size: output_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        self.layout.num_preimages = preimages.len() as u32;
+        let layout_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("poseidon-layout"),
+            contents: bytemuck::bytes_of(&self.layout),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("poseidon-bind-group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: input_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.constants_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: layout_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let workgroups = (preimages.len() as u64 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+            pass.dispatch_workgroups(workgroups.max(1) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output_buffer, 0, &staging_buffer, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| Error::GPUError(format!("WebGPU map_async channel closed: {}", e)))?
+            .map_err(|e| Error::GPUError(format!("failed to map WebGPU output buffer: {}", e)))?;
+
+        let data = slice.get_mapped_range();
+        let results = data
+            .chunks_exact(size_of::<Fr>())
+            .take(preimages.len())
+            .map(|chunk| {
+                let mut repr = <Fr as PrimeField>::Repr::default();
+                repr.read_le(chunk)
+                    .map_err(|e| Error::GPUError(format!("invalid Fr bytes from WebGPU kernel: {}", e)))?;
+                Fr::from_repr(repr)
+                    .map_err(|e| Error::GPUError(format!("invalid Fr returned from WebGPU kernel: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        drop(data);
+        staging_buffer.unmap();
+
+        Ok(results)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "webgpu")]
+mod tests {
+    use super::*;
+    use crate::poseidon::SimplePoseidonBatchHasher;
+    use crate::typenum::{U2, U4, U8, U11};
+    use generic_array::sequence::GenericSequence;
+
+    fn sample_preimages<A: Arity<Fr>>(count: usize) -> Vec<GenericArray<Fr, A>> {
+        (0..count)
+            .map(|i| {
+                GenericArray::generate(|j| {
+                    Fr::from_str(&(i * 1000 + j + 1).to_string()).expect("valid Fr literal")
+                })
+            })
+            .collect()
+    }
+
+    fn assert_matches_cpu<A: Arity<Fr>>() {
+        let preimages = sample_preimages::<A>(37);
+
+        let mut wgpu_hasher =
+            WgpuBatchHasher::<A>::new_with_strength(Strength::Standard, preimages.len())
+                .expect("a WebGPU adapter is available in CI");
+        let mut cpu_hasher =
+            SimplePoseidonBatchHasher::<A>::new_with_strength(Strength::Standard, preimages.len())
+                .expect("CPU hasher always available");
+
+        let gpu_digests = wgpu_hasher.hash(&preimages).expect("WebGPU hash succeeds");
+        let cpu_digests = cpu_hasher.hash(&preimages).expect("CPU hash succeeds");
+
+        assert_eq!(gpu_digests, cpu_digests);
+    }
+
+    #[test]
+    fn wgpu_matches_cpu_arity_2() {
+        assert_matches_cpu::<U2>();
+    }
+
+    #[test]
+    fn wgpu_matches_cpu_arity_4() {
+        assert_matches_cpu::<U4>();
+    }
+
+    #[test]
+    fn wgpu_matches_cpu_arity_8() {
+        assert_matches_cpu::<U8>();
+    }
+
+    #[test]
+    fn wgpu_matches_cpu_arity_11() {
+        assert_matches_cpu::<U11>();
+    }
+
+    #[test]
+    fn device_info_reports_a_nonzero_workgroup_size() {
+        let hasher = WgpuBatchHasher::<U2>::new_with_strength(Strength::Standard, 10)
+            .expect("a WebGPU adapter is available in CI");
+        let info = hasher.device_info();
+        assert!(info.max_workgroup_size > 0);
+        assert!(info.recommended_batch_size(32) > 0);
+    }
+}